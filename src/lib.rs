@@ -1,6 +1,10 @@
-use std::{collections::VecDeque, str::FromStr};
+use std::{
+    collections::{BinaryHeap, VecDeque},
+    str::FromStr,
+    time::{Duration, Instant},
+};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct Pos(i32, i32);
@@ -9,6 +13,10 @@ impl Pos {
     fn to_index(&self, map_size: (usize, usize)) -> usize {
         self.1 as usize * map_size.0 + self.0 as usize
     }
+
+    fn manhattan(&self, other: &Pos) -> u32 {
+        self.0.abs_diff(other.0) + self.1.abs_diff(other.1)
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -17,6 +25,10 @@ pub enum Direction {
     South,
     East,
     West,
+    NorthEast,
+    NorthWest,
+    SouthEast,
+    SouthWest,
 }
 
 impl Direction {
@@ -26,6 +38,71 @@ impl Direction {
             Direction::South => Pos(pos.0, pos.1 - 1),
             Direction::East => Pos(pos.0 + 1, pos.1),
             Direction::West => Pos(pos.0 - 1, pos.1),
+            Direction::NorthEast => Pos(pos.0 + 1, pos.1 + 1),
+            Direction::NorthWest => Pos(pos.0 - 1, pos.1 + 1),
+            Direction::SouthEast => Pos(pos.0 + 1, pos.1 - 1),
+            Direction::SouthWest => Pos(pos.0 - 1, pos.1 - 1),
+        }
+    }
+
+    fn is_diagonal(&self) -> bool {
+        matches!(
+            self,
+            Direction::NorthEast
+                | Direction::NorthWest
+                | Direction::SouthEast
+                | Direction::SouthWest
+        )
+    }
+}
+
+const FOUR_DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::South,
+    Direction::East,
+    Direction::West,
+];
+
+const EIGHT_DIRECTIONS: [Direction; 8] = [
+    Direction::North,
+    Direction::South,
+    Direction::East,
+    Direction::West,
+    Direction::NorthEast,
+    Direction::NorthWest,
+    Direction::SouthEast,
+    Direction::SouthWest,
+];
+
+/// Search strategy used by [`PhoenicianTrader`] to find the next port.
+///
+/// `Bfs` is only optimal while every navigable tile costs the same; once a
+/// map mixes in `Current`/`Shallow` tiles, switch to `Dijkstra` or `AStar`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SearchMode {
+    #[default]
+    Bfs,
+    Dijkstra,
+    AStar,
+}
+
+/// How many directions a tile can be entered from, and the relative cost of
+/// diagonal steps versus orthogonal ones.
+///
+/// `EightWay`'s defaults (5 straight / 7 diagonal) are an integer
+/// approximation of `1 : sqrt(2)`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Movement {
+    #[default]
+    FourWay,
+    EightWay { straight_cost: u32, diagonal_cost: u32 },
+}
+
+impl Movement {
+    pub fn eight_way() -> Self {
+        Movement::EightWay {
+            straight_cost: 5,
+            diagonal_cost: 7,
         }
     }
 }
@@ -35,6 +112,35 @@ enum WorldMapNode {
     Water,
     Land,
     Port(usize),
+    /// A water current, costlier to cross than open water.
+    Current(u32),
+    /// A shallow, also costlier than open water.
+    Shallow(u32),
+}
+
+impl WorldMapNode {
+    /// Traversal cost of this tile, or `None` if it cannot be sailed through.
+    fn cost(&self) -> Option<u32> {
+        match self {
+            WorldMapNode::Water | WorldMapNode::Port(_) => Some(1),
+            WorldMapNode::Current(cost) | WorldMapNode::Shallow(cost) => Some(*cost),
+            WorldMapNode::Land => None,
+        }
+    }
+}
+
+/// How often a search reports progress to its [`with_progress`](PhoenicianTrader::with_progress) callback.
+const STATUS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// A snapshot of an in-progress search, handed to the callback registered
+/// via [`PhoenicianTrader::with_progress`].
+#[derive(Clone, Copy, Debug)]
+pub struct SearchState {
+    pub current_port_id: usize,
+    pub queue_size: usize,
+    pub depth: usize,
+    pub visited_count: usize,
+    pub percent_seen: f64,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -59,13 +165,354 @@ pub struct PhoenicianTrader {
     world_map: Vec<WorldMapNode>,
     map_size: (usize, usize),
     fuel_cost: usize,
+    search_mode: SearchMode,
+    movement: Movement,
+    last_parents: Vec<Option<Pos>>,
+    navigable_tiles: usize,
+    progress: Option<Box<dyn FnMut(SearchState)>>,
+}
+
+impl PhoenicianTrader {
+    /// Selects the search strategy used to reach the next port.
+    pub fn with_search_mode(mut self, search_mode: SearchMode) -> Self {
+        self.search_mode = search_mode;
+        self
+    }
+
+    /// Selects whether the trader may cut diagonally across open water.
+    pub fn with_movement(mut self, movement: Movement) -> Self {
+        self.movement = movement;
+        self
+    }
+
+    /// Registers a callback invoked roughly every [`STATUS_INTERVAL`] while
+    /// a search is in flight, useful for reporting live progress on large
+    /// maps.
+    pub fn with_progress(mut self, progress: Box<dyn FnMut(SearchState)>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    fn report_progress(
+        &mut self,
+        current_port_id: usize,
+        queue_size: usize,
+        depth: usize,
+        visited: &[Option<usize>],
+    ) {
+        let Some(progress) = self.progress.as_mut() else {
+            return;
+        };
+
+        let visited_count = visited.iter().filter(|distance| distance.is_some()).count();
+        let percent_seen = if self.navigable_tiles == 0 {
+            0.0
+        } else {
+            visited_count as f64 / self.navigable_tiles as f64
+        };
+
+        progress(SearchState {
+            current_port_id,
+            queue_size,
+            depth,
+            visited_count,
+            percent_seen,
+        });
+    }
+
+    fn tile_cost(&self, pos: Pos) -> u32 {
+        self.world_map[pos.to_index(self.map_size)]
+            .cost()
+            .unwrap_or(1)
+    }
+
+    /// Cost of stepping `direction` onto `next_pos`: the tile's own cost
+    /// scaled by the movement model's straight/diagonal weighting.
+    fn step_cost(&self, direction: Direction, next_pos: Pos) -> u32 {
+        let direction_cost = match self.movement {
+            Movement::FourWay => 1,
+            Movement::EightWay {
+                straight_cost,
+                diagonal_cost,
+            } => {
+                if direction.is_diagonal() {
+                    diagonal_cost
+                } else {
+                    straight_cost
+                }
+            }
+        };
+
+        direction_cost * self.tile_cost(next_pos)
+    }
+
+    /// Cheapest cost of any navigable tile on the map, used to keep the
+    /// `AStar` heuristic admissible.
+    fn min_tile_cost(&self) -> u32 {
+        self.world_map
+            .iter()
+            .filter_map(WorldMapNode::cost)
+            .min()
+            .unwrap_or(1)
+    }
+
+    /// Admissible distance estimate between two tiles under the trader's
+    /// movement model: Manhattan distance for `FourWay`, octile distance
+    /// (accounting for cheaper diagonal coverage) for `EightWay`.
+    fn geometry_distance(&self, from: Pos, to: Pos) -> u32 {
+        let dx = from.0.abs_diff(to.0) as i64;
+        let dy = from.1.abs_diff(to.1) as i64;
+
+        let distance = match self.movement {
+            Movement::FourWay => dx + dy,
+            Movement::EightWay {
+                straight_cost,
+                diagonal_cost,
+            } => {
+                let straight_cost = straight_cost as i64;
+                let diagonal_cost = diagonal_cost as i64;
+
+                // The `(diagonal_cost - 2 * straight_cost)` term is
+                // legitimately negative for a realistic diagonal weighting
+                // (e.g. 7 vs 5): two straight steps already cover the same
+                // displacement as one diagonal step, so the formula must be
+                // able to subtract back the overcounted straight cost to
+                // stay admissible.
+                straight_cost * (dx + dy) + (diagonal_cost - 2 * straight_cost) * dx.min(dy)
+            }
+        };
+
+        distance.max(0) as u32
+    }
+
+    fn neighbors(&self, node: Pos) -> impl Iterator<Item = (Direction, Pos)> + '_ {
+        let directions: &[Direction] = match self.movement {
+            Movement::FourWay => &FOUR_DIRECTIONS,
+            Movement::EightWay { .. } => &EIGHT_DIRECTIONS,
+        };
+
+        directions.iter().filter_map(move |direction| {
+            let Pos(x, y) = direction.to_pos(&node);
+
+            if x < 0 || y < 0 || x >= self.map_size.0 as i32 || y >= self.map_size.1 as i32 {
+                return None;
+            }
+
+            let next_node = Pos(x, y);
+
+            match self.world_map[next_node.to_index(self.map_size)] {
+                WorldMapNode::Land => None,
+                _ => Some((*direction, next_node)),
+            }
+        })
+    }
+
+    /// Records `node` as a candidate next port (if it is one) and reports
+    /// whether the search can stop early because every remaining port has
+    /// already been found.
+    fn visit_node(
+        &mut self,
+        node: Pos,
+        distance: usize,
+        current_port_id: usize,
+        ports: &mut Vec<(Pos, usize)>,
+        left_ports: &mut Vec<Pos>,
+    ) -> bool {
+        if let WorldMapNode::Port(port) = self.world_map[node.to_index(self.map_size)] {
+            if port > current_port_id {
+                ports.push((node, distance));
+
+                if self.current_port == self.first_port {
+                    self.left_ports.push(node);
+                } else {
+                    left_ports.retain(|&p| p != node);
+                }
+            }
+        }
+
+        left_ports.is_empty() && self.current_port != self.first_port
+    }
+
+    /// Shortest cost from `start` to every tile, via Dijkstra relaxation
+    /// over the weighted water tiles (open water, currents, shallows).
+    fn distances_from(&self, start: Pos) -> Vec<Option<usize>> {
+        let mut visited: Vec<Option<usize>> = vec![None; self.map_size.0 * self.map_size.1];
+        visited[start.to_index(self.map_size)] = Some(0);
+
+        let mut heap = BinaryHeap::new();
+        heap.push(PosWithDistance(start, 0));
+
+        while let Some(PosWithDistance(node, _priority)) = heap.pop() {
+            let index = node.to_index(self.map_size);
+            let distance = match visited[index] {
+                Some(distance) => distance,
+                None => continue,
+            };
+
+            for (direction, next_node) in self.neighbors(node) {
+                let next_index = next_node.to_index(self.map_size);
+                let next_distance = distance + self.step_cost(direction, next_node) as usize;
+
+                let is_cheaper = match visited[next_index] {
+                    Some(known) => next_distance < known,
+                    None => true,
+                };
+
+                if is_cheaper {
+                    visited[next_index] = Some(next_distance);
+                    heap.push(PosWithDistance(next_node, next_distance));
+                }
+            }
+        }
+
+        visited
+    }
+
+    /// Finds the minimum-fuel route that visits every port, solving it as a
+    /// TSP path with Held-Karp dynamic programming instead of the
+    /// order-constrained heuristic the iterator uses.
+    ///
+    /// Builds an `n x n` symmetric distance matrix (one Dijkstra per port,
+    /// `n` bounded by the parser's single-digit port ids), then computes
+    /// `dp[mask][j]` = cheapest cost to start at the lowest-id port, visit
+    /// exactly the ports in `mask`, and end at port `j`.
+    ///
+    /// Returns an error if any two ports aren't connected by water (e.g.
+    /// two separate landlocked bodies of water).
+    pub fn optimal_tour(&self) -> Result<(usize, Vec<Pos>)> {
+        let mut ports: Vec<Pos> = self
+            .world_map
+            .iter()
+            .enumerate()
+            .filter_map(|(index, node)| match node {
+                WorldMapNode::Port(_) => Some(Pos(
+                    (index % self.map_size.0) as i32,
+                    (index / self.map_size.0) as i32,
+                )),
+                _ => None,
+            })
+            .collect();
+
+        ports.sort_by_key(|port| match self.world_map[port.to_index(self.map_size)] {
+            WorldMapNode::Port(id) => id,
+            _ => unreachable!("Should be a port"),
+        });
+
+        let n = ports.len();
+
+        if n <= 1 {
+            return Ok((0, ports));
+        }
+
+        let mut dist = vec![vec![0; n]; n];
+        for (i, &port) in ports.iter().enumerate() {
+            let distances = self.distances_from(port);
+
+            for (j, &other) in ports.iter().enumerate() {
+                dist[i][j] = distances[other.to_index(self.map_size)].ok_or(
+                    TourError::UnreachablePort {
+                        from: port,
+                        to: other,
+                    },
+                )?;
+            }
+        }
+
+        let full_mask = 1 << n;
+        let mut dp = vec![vec![usize::MAX; n]; full_mask];
+        let mut parent = vec![vec![usize::MAX; n]; full_mask];
+
+        dp[1][0] = 0;
+
+        for mask in 1..full_mask {
+            if mask & 1 == 0 {
+                continue;
+            }
+
+            for j in 0..n {
+                if mask & (1 << j) == 0 || dp[mask][j] == usize::MAX {
+                    continue;
+                }
+
+                for k in 0..n {
+                    if mask & (1 << k) != 0 {
+                        continue;
+                    }
+
+                    let next_mask = mask | (1 << k);
+                    let cost = dp[mask][j] + dist[j][k];
+
+                    if cost < dp[next_mask][k] {
+                        dp[next_mask][k] = cost;
+                        parent[next_mask][k] = j;
+                    }
+                }
+            }
+        }
+
+        let full = full_mask - 1;
+        let (end, cost) = dp[full]
+            .iter()
+            .enumerate()
+            .min_by_key(|&(_, &cost)| cost)
+            .map(|(index, &cost)| (index, cost))
+            .expect("n > 1, so dp[full] is non-empty");
+
+        let mut order = Vec::with_capacity(n);
+        let mut mask = full;
+        let mut node = end;
+
+        loop {
+            order.push(ports[node]);
+
+            let prev = parent[mask][node];
+            if prev == usize::MAX {
+                break;
+            }
+
+            mask &= !(1 << node);
+            node = prev;
+        }
+
+        order.reverse();
+
+        Ok((cost, order))
+    }
+
+    /// Reconstructs the tile-by-tile route from the previous port to the
+    /// port reached by the most recent call to [`next`](Iterator::next), by
+    /// walking the search's predecessor map backward from `current_port`.
+    pub fn last_leg_path(&self) -> Vec<Pos> {
+        let mut path = Vec::new();
+        let mut node = Some(self.current_port);
+
+        while let Some(pos) = node {
+            path.push(pos);
+            node = self.last_parents[pos.to_index(self.map_size)];
+        }
+
+        path.reverse();
+        path
+    }
+
+    /// Drains the iterator, returning every port hop together with the
+    /// concrete path sailed to reach it.
+    pub fn full_route(&mut self) -> Vec<(Pos, Vec<Pos>)> {
+        let mut route = Vec::new();
+
+        while self.next().is_some() {
+            route.push((self.current_port, self.last_leg_path()));
+        }
+
+        route
+    }
 }
 
 impl Iterator for PhoenicianTrader {
     type Item = usize;
     fn next(&mut self) -> Option<Self::Item> {
-        let mut visited = vec![None; self.map_size.0 * self.map_size.1];
-        let mut queue = VecDeque::new();
+        let mut visited: Vec<Option<usize>> = vec![None; self.map_size.0 * self.map_size.1];
+        let mut parents: Vec<Option<Pos>> = vec![None; self.map_size.0 * self.map_size.1];
 
         let mut ports = Vec::new();
 
@@ -74,49 +521,107 @@ impl Iterator for PhoenicianTrader {
             _ => unreachable!("Should be a port"),
         };
 
-        queue.push_front(PosWithDistance(self.current_port, 0));
+        let mut left_ports = self.left_ports.clone();
+
         visited[self.current_port.to_index(self.map_size)] = Some(0);
 
-        let mut left_ports = self.left_ports.clone();
+        match self.search_mode {
+            SearchMode::Bfs => {
+                let mut queue = VecDeque::new();
+                queue.push_front(PosWithDistance(self.current_port, 0));
+
+                let mut depth = 0;
+                let mut last_report = Instant::now();
+
+                while let Some(PosWithDistance(node, distance)) = queue.pop_back() {
+                    depth = depth.max(distance);
+
+                    if last_report.elapsed() >= STATUS_INTERVAL {
+                        self.report_progress(current_port_id, queue.len(), depth, &visited);
+                        last_report = Instant::now();
+                    }
+
+                    if self.visit_node(node, distance, current_port_id, &mut ports, &mut left_ports) {
+                        break;
+                    }
 
-        while let Some(PosWithDistance(node, distance)) = queue.pop_back() {
-            if let WorldMapNode::Port(port) = self.world_map[node.to_index(self.map_size)] {
-                if port > current_port_id {
-                    ports.push((node, distance));
+                    for (_, next_node) in self.neighbors(node) {
+                        let index = next_node.to_index(self.map_size);
 
-                    if self.current_port == self.first_port {
-                        self.left_ports.push(node);
-                    } else {
-                        left_ports.retain(|&port| port != node);
+                        if visited[index].is_none() {
+                            visited[index] = Some(distance + 1);
+                            parents[index] = Some(node);
+                            queue.push_front(PosWithDistance(next_node, distance + 1));
+                        }
                     }
                 }
             }
+            SearchMode::Dijkstra | SearchMode::AStar => {
+                // A* needs a single target to stay admissible; we aim the
+                // heuristic at the closest still-unvisited port and let the
+                // rest of the search fall out of the same relaxation as
+                // Dijkstra, since several ports are collected in one pass.
+                // On the first leg no remaining port is known yet, so no
+                // target is set and the search runs as plain Dijkstra.
+                let target = (self.search_mode == SearchMode::AStar)
+                    .then(|| {
+                        left_ports
+                            .iter()
+                            .min_by_key(|port| self.current_port.manhattan(port))
+                            .copied()
+                    })
+                    .flatten();
 
-            if left_ports.is_empty() && self.current_port != self.first_port {
-                break;
-            }
+                let min_tile_cost = self.min_tile_cost() as usize;
 
-            for direction in &[
-                Direction::North,
-                Direction::South,
-                Direction::East,
-                Direction::West,
-            ] {
-                let next_node = direction.to_pos(&node);
+                let mut heap = BinaryHeap::new();
+                heap.push(PosWithDistance(self.current_port, 0));
 
-                let Pos(x, y) = next_node;
+                let mut depth = 0;
+                let mut last_report = Instant::now();
 
-                if x < 0 || y < 0 || x >= self.map_size.0 as i32 || y >= self.map_size.1 as i32 {
-                    continue;
-                }
+                while let Some(PosWithDistance(node, _priority)) = heap.pop() {
+                    let index = node.to_index(self.map_size);
+                    let distance = match visited[index] {
+                        Some(distance) => distance,
+                        None => continue,
+                    };
 
-                if let WorldMapNode::Land = self.world_map[next_node.to_index(self.map_size)] {
-                    continue;
-                }
+                    depth = depth.max(distance);
+
+                    if last_report.elapsed() >= STATUS_INTERVAL {
+                        self.report_progress(current_port_id, heap.len(), depth, &visited);
+                        last_report = Instant::now();
+                    }
+
+                    if self.visit_node(node, distance, current_port_id, &mut ports, &mut left_ports) {
+                        break;
+                    }
+
+                    for (direction, next_node) in self.neighbors(node) {
+                        let next_index = next_node.to_index(self.map_size);
+                        let next_distance = distance + self.step_cost(direction, next_node) as usize;
 
-                if visited[next_node.to_index(self.map_size)].is_none() {
-                    queue.push_front(PosWithDistance(next_node, distance + 1));
-                    visited[next_node.to_index(self.map_size)] = Some(distance + 1);
+                        let is_cheaper = match visited[next_index] {
+                            Some(known) => next_distance < known,
+                            None => true,
+                        };
+
+                        if is_cheaper {
+                            visited[next_index] = Some(next_distance);
+                            parents[next_index] = Some(node);
+
+                            let priority = match target {
+                                Some(target) => {
+                                    next_distance
+                                        + self.geometry_distance(next_node, target) as usize * min_tile_cost
+                                }
+                                None => next_distance,
+                            };
+
+                            heap.push(PosWithDistance(next_node, priority));
+                        }
+                    }
                 }
             }
         }
@@ -149,11 +654,72 @@ impl Iterator for PhoenicianTrader {
 
         self.fuel_cost += distance;
         self.current_port = port;
+        self.last_parents = parents;
 
         Some(self.fuel_cost)
     }
 }
 
+/// Describes why [`PhoenicianTrader::optimal_tour`] could not compute a
+/// route.
+#[derive(Debug)]
+pub enum TourError {
+    UnreachablePort { from: Pos, to: Pos },
+}
+
+impl std::fmt::Display for TourError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TourError::UnreachablePort { from, to } => {
+                write!(f, "no water route from port at {from:?} to port at {to:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TourError {}
+
+/// Describes why a map string failed to parse, with enough detail for a
+/// caller to report which file and tile caused the failure.
+#[derive(Debug)]
+pub enum ParseError {
+    MissingHeader,
+    MalformedHeader(String),
+    RowCountMismatch { expected: usize, found: usize },
+    RowWidthMismatch { row: usize, expected: usize, found: usize },
+    UnknownTile { x: usize, y: usize, ch: char },
+    NoPorts,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::MissingHeader => write!(f, "missing map dimension header"),
+            ParseError::MalformedHeader(header) => write!(
+                f,
+                "malformed dimension header '{header}', expected '<width> <height>'"
+            ),
+            ParseError::RowCountMismatch { expected, found } => {
+                write!(f, "expected {expected} rows, found {found}")
+            }
+            ParseError::RowWidthMismatch {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "row {row} has width {found}, expected {expected}"
+            ),
+            ParseError::UnknownTile { x, y, ch } => {
+                write!(f, "bad tile '{ch}' at ({x}, {y})")
+            }
+            ParseError::NoPorts => write!(f, "map has no ports"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 impl FromStr for PhoenicianTrader {
     type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self> {
@@ -162,16 +728,42 @@ impl FromStr for PhoenicianTrader {
 
         let mut lines = s.lines();
 
-        let map_size: (usize, usize) = lines
-            .next()
-            .unwrap()
-            .trim()
+        let header = lines.next().ok_or(ParseError::MissingHeader)?.trim();
+
+        let (width_str, height_str) = header
             .split_once(' ')
-            .map(|(x, y)| (y.parse().unwrap(), x.parse().unwrap()))
-            .unwrap();
+            .ok_or_else(|| ParseError::MalformedHeader(header.to_owned()))?;
+
+        let width: usize = width_str
+            .parse()
+            .map_err(|_| ParseError::MalformedHeader(header.to_owned()))?;
+        let height: usize = height_str
+            .parse()
+            .map_err(|_| ParseError::MalformedHeader(header.to_owned()))?;
 
-        for (y, line) in lines.enumerate() {
-            for (x, ch) in line.trim().chars().enumerate() {
+        let rows: Vec<&str> = lines.map(str::trim).collect();
+
+        if rows.len() != height {
+            return Err(ParseError::RowCountMismatch {
+                expected: height,
+                found: rows.len(),
+            }
+            .into());
+        }
+
+        for (y, row) in rows.into_iter().enumerate() {
+            let row_width = row.chars().count();
+
+            if row_width != width {
+                return Err(ParseError::RowWidthMismatch {
+                    row: y,
+                    expected: width,
+                    found: row_width,
+                }
+                .into());
+            }
+
+            for (x, ch) in row.chars().enumerate() {
                 let pos = Pos(x as i32, y as i32);
 
                 match ch {
@@ -181,16 +773,33 @@ impl FromStr for PhoenicianTrader {
                     '*' => {
                         world_map.push(WorldMapNode::Land);
                     }
+                    '~' => {
+                        world_map.push(WorldMapNode::Current(3));
+                    }
+                    '-' => {
+                        world_map.push(WorldMapNode::Shallow(2));
+                    }
                     '0'..='9' => {
-                        world_map.push(WorldMapNode::Port(ch.to_digit(10).unwrap() as usize));
-                        ports.push((pos, ch.to_digit(10).unwrap() as usize));
+                        let port_id = ch.to_digit(10).unwrap() as usize;
+                        world_map.push(WorldMapNode::Port(port_id));
+                        ports.push((pos, port_id));
                     }
-                    _ => unreachable!("Invalid character"),
+                    ch => return Err(ParseError::UnknownTile { x, y, ch }.into()),
                 }
             }
         }
 
-        let current_port = ports.iter().min_by_key(|(_, port)| *port).unwrap().0;
+        // `Pos::to_index`/`neighbors` stride by `map_size.0` as the row width,
+        // so this must be `(width, height)`, not the map's `(rows, cols)` shape.
+        let map_size = (width, height);
+
+        let current_port = ports
+            .iter()
+            .min_by_key(|(_, port)| *port)
+            .context(ParseError::NoPorts)?
+            .0;
+
+        let navigable_tiles = world_map.iter().filter(|node| node.cost().is_some()).count();
 
         Ok(Self {
             first_port: current_port,
@@ -198,7 +807,12 @@ impl FromStr for PhoenicianTrader {
             left_ports: Vec::new(),
             world_map,
             fuel_cost: 0,
+            last_parents: vec![None; map_size.0 * map_size.1],
             map_size,
+            search_mode: SearchMode::default(),
+            movement: Movement::default(),
+            navigable_tiles,
+            progress: None,
         })
     }
 }
@@ -237,6 +851,197 @@ mod tests {
         assert_eq!(phoenicians.fuel_cost, 126);
     }
 
+    #[test]
+    fn dijkstra_prefers_cheaper_route_over_bfs_hop_count() {
+        const INPUT: &str = "5 5\n0~~~1\n.....\n.....\n.....\n.....";
+
+        let mut bfs: PhoenicianTrader = INPUT.parse().unwrap();
+        let bfs_cost = bfs.next().unwrap();
+
+        let mut dijkstra: PhoenicianTrader = INPUT
+            .parse::<PhoenicianTrader>()
+            .unwrap()
+            .with_search_mode(SearchMode::Dijkstra);
+        let dijkstra_cost = dijkstra.next().unwrap();
+
+        let mut astar: PhoenicianTrader = INPUT
+            .parse::<PhoenicianTrader>()
+            .unwrap()
+            .with_search_mode(SearchMode::AStar);
+        let astar_cost = astar.next().unwrap();
+
+        // Bfs only counts hops, so it takes the 4-hop straight line through
+        // the costly currents; Dijkstra/AStar pay two extra hops to detour
+        // through the open water below for a genuinely cheaper total.
+        assert_eq!(bfs_cost, 8);
+        assert_eq!(dijkstra_cost, 12);
+        assert_eq!(astar_cost, dijkstra_cost);
+    }
+
+    #[test]
+    fn optimal_tour_reorders_ports_by_distance_not_id() {
+        const INPUT: &str = "9 1\n0..2....1";
+
+        let trader: PhoenicianTrader = INPUT.parse().unwrap();
+        let (cost, order) = trader.optimal_tour().unwrap();
+
+        // Visiting port 2 before port 1 (3 + 5) beats the id order (8 + 5).
+        assert_eq!(cost, 8);
+        assert_eq!(order, vec![Pos(0, 0), Pos(3, 0), Pos(8, 0)]);
+    }
+
+    #[test]
+    fn optimal_tour_errors_on_unreachable_port() {
+        const INPUT: &str = "5 3\n0****\n*****\n****1";
+
+        let trader: PhoenicianTrader = INPUT.parse().unwrap();
+
+        assert!(trader.optimal_tour().is_err());
+    }
+
+    #[test]
+    fn last_leg_path_and_full_route_reconstruct_the_sailed_tiles() {
+        const INPUT: &str = "5 1\n0...1";
+
+        let mut trader: PhoenicianTrader = INPUT.parse().unwrap();
+        trader.next();
+
+        assert_eq!(
+            trader.last_leg_path(),
+            vec![Pos(0, 0), Pos(1, 0), Pos(2, 0), Pos(3, 0), Pos(4, 0)]
+        );
+
+        let mut trader: PhoenicianTrader = INPUT.parse().unwrap();
+        assert_eq!(
+            trader.full_route(),
+            vec![(
+                Pos(4, 0),
+                vec![Pos(0, 0), Pos(1, 0), Pos(2, 0), Pos(3, 0), Pos(4, 0)]
+            )]
+        );
+    }
+
+    fn parse_error(input: &str) -> ParseError {
+        let err = match input.parse::<PhoenicianTrader>() {
+            Ok(_) => panic!("expected a parse error"),
+            Err(err) => err,
+        };
+
+        err.downcast::<ParseError>().unwrap()
+    }
+
+    #[test]
+    fn parse_reports_missing_header() {
+        assert!(matches!(parse_error(""), ParseError::MissingHeader));
+    }
+
+    #[test]
+    fn parse_reports_malformed_header() {
+        assert!(matches!(
+            parse_error("notaheader"),
+            ParseError::MalformedHeader(header) if header == "notaheader"
+        ));
+    }
+
+    #[test]
+    fn parse_reports_row_count_mismatch() {
+        assert!(matches!(
+            parse_error("5 2\n....."),
+            ParseError::RowCountMismatch {
+                expected: 2,
+                found: 1
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_reports_row_width_mismatch() {
+        assert!(matches!(
+            parse_error("5 1\n...."),
+            ParseError::RowWidthMismatch {
+                row: 0,
+                expected: 5,
+                found: 4
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_reports_unknown_tile() {
+        assert!(matches!(
+            parse_error("5 1\n..?.."),
+            ParseError::UnknownTile { x: 2, y: 0, ch: '?' }
+        ));
+    }
+
+    #[test]
+    fn parse_reports_no_ports() {
+        assert!(matches!(parse_error("5 1\n....."), ParseError::NoPorts));
+    }
+
+    #[test]
+    fn report_progress_invokes_registered_callback() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        const INPUT: &str = "5 1\n0...1";
+
+        let mut trader: PhoenicianTrader = INPUT.parse().unwrap();
+
+        let captured: Rc<RefCell<Option<SearchState>>> = Rc::new(RefCell::new(None));
+        let captured_handle = captured.clone();
+        trader = trader.with_progress(Box::new(move |state| {
+            *captured_handle.borrow_mut() = Some(state);
+        }));
+
+        let visited = vec![Some(0), Some(1), None, None, None];
+        trader.report_progress(0, 3, 1, &visited);
+
+        let state = captured.borrow().expect("callback should have been invoked");
+
+        assert_eq!(state.current_port_id, 0);
+        assert_eq!(state.queue_size, 3);
+        assert_eq!(state.depth, 1);
+        assert_eq!(state.visited_count, 2);
+        assert_eq!(state.percent_seen, 2.0 / trader.navigable_tiles as f64);
+    }
+
+    #[test]
+    fn eight_way_diagonal_step_costs_diagonal_cost_under_dijkstra() {
+        const INPUT: &str = "2 2\n0.\n.1";
+
+        // SearchMode::Bfs ignores step_cost entirely (it just counts hops),
+        // so the diagonal weighting only shows up under Dijkstra/AStar.
+        let mut four_way: PhoenicianTrader = INPUT
+            .parse::<PhoenicianTrader>()
+            .unwrap()
+            .with_search_mode(SearchMode::Dijkstra);
+        let four_way_cost = four_way.next().unwrap();
+
+        let mut eight_way: PhoenicianTrader = INPUT
+            .parse::<PhoenicianTrader>()
+            .unwrap()
+            .with_search_mode(SearchMode::Dijkstra)
+            .with_movement(Movement::eight_way());
+        let eight_way_cost = eight_way.next().unwrap();
+
+        let mut eight_way_astar: PhoenicianTrader = INPUT
+            .parse::<PhoenicianTrader>()
+            .unwrap()
+            .with_search_mode(SearchMode::AStar)
+            .with_movement(Movement::eight_way());
+        let eight_way_astar_cost = eight_way_astar.next().unwrap();
+
+        // FourWay needs two orthogonal hops (cost 1 each); EightWay cuts the
+        // same corner in one diagonal hop costing `diagonal_cost` (7), not
+        // the straight_cost (5) or a uniform hop count.
+        assert_eq!(four_way_cost, 4);
+        assert_eq!(eight_way_cost, 14);
+        // On this first leg no remaining port is known yet, so AStar runs
+        // without a heuristic and gives the same cost as Dijkstra.
+        assert_eq!(eight_way_astar_cost, eight_way_cost);
+    }
+
     #[test]
     fn test_input() {
         let input = fs::read_to_string("./cases/caso20.txt").unwrap();
@@ -18,7 +18,13 @@ fn main() {
         let path = file.unwrap().path();
         let input = std::fs::read_to_string(&path).unwrap();
 
-        let phoenicians: PhoenicianTrader = input.parse().unwrap();
+        let phoenicians: PhoenicianTrader = match input.parse() {
+            Ok(phoenicians) => phoenicians,
+            Err(err) => {
+                println!("{:?}: {err}", path);
+                continue;
+            }
+        };
 
         let start = std::time::Instant::now();
 